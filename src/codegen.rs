@@ -0,0 +1,578 @@
+// Lowers a type-checked, monomorphic `Expr` to LLVM IR via `inkwell` and
+// emits a native object file, as an alternative backend to the
+// tree-walking `eval`. `eval` remains the reference semantics this
+// backend is checked against in differential testing.
+//
+// Closures are only ever called directly out of the value they were
+// produced as (an `App` whose `abs` side evaluates straight to a
+// `CgValue::Closure`), never passed through a generic LLVM value slot
+// (a function return, a struct field, ...). That's enough for the
+// monomorphic programs this backend targets; first-class closures
+// crossing such a boundary are out of scope here.
+//
+// A closure's generated LLVM function always has signature
+// `(env ptr, i64) -> i64`: its parameter must be explicitly annotated
+// `Int` and its body must evaluate to an `Int`. Anything else (an
+// unannotated or `Str`-typed parameter, a `Str`-typed body, ...) is
+// rejected with a `CodegenError` up front rather than silently lowered
+// into mistyped IR.
+//
+// Built against inkwell's `llvm14-0` feature (typed, not opaque,
+// pointers) — see Cargo.toml for the system LLVM version this needs.
+
+use std::collections::HashMap as NativeMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use inkwell::builder::Builder;
+use inkwell::context::Context;
+use inkwell::module::Module;
+use inkwell::targets::{
+    CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine,
+};
+use inkwell::types::BasicTypeEnum;
+use inkwell::values::{BasicValueEnum, FunctionValue, IntValue, PointerValue};
+use inkwell::{AddressSpace, OptimizationLevel};
+
+use crate::{infer, replace_type, Error, Expr, Type, TypeContext};
+
+// Failure modes specific to the compiler backend. Type errors surface
+// through the existing `Error` type; everything from monomorphization
+// onward gets its own variants, since they have no equivalent in the
+// interpreter.
+#[derive(Debug)]
+pub enum CodegenError {
+    Typecheck(Error),
+    NotCompilable { found: Type },
+    UnresolvedPolymorphism { param: String },
+    UnsupportedClosureParam { param_type: Option<Type> },
+    UnsupportedClosureReturn,
+    Llvm(String),
+}
+
+impl Display for CodegenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodegenError::Typecheck(error) => write!(f, "{}", error),
+            CodegenError::NotCompilable { found } => {
+                write!(f, "cannot compile a program of type {}, only Int", found)
+            }
+            CodegenError::UnresolvedPolymorphism { param } => write!(
+                f,
+                "type variable {} is still polymorphic after monomorphization",
+                param
+            ),
+            CodegenError::UnsupportedClosureParam {
+                param_type: Some(found),
+            } => write!(
+                f,
+                "closures over {} are not supported by this backend, only Int",
+                found
+            ),
+            CodegenError::UnsupportedClosureParam { param_type: None } => write!(
+                f,
+                "closures need an explicit Int parameter annotation to compile"
+            ),
+            CodegenError::UnsupportedClosureReturn => {
+                write!(f, "closures must return an Int to compile")
+            }
+            CodegenError::Llvm(message) => f.write_str(message),
+        }
+    }
+}
+
+// Specializes every `TypeApp`/`TypeAbs` pair by substituting the applied
+// type through the body (reusing `replace_type`), so that afterwards every
+// subterm has a ground type and codegen never has to reason about
+// `Forall`. A `TypeAbs` that survives this pass was never applied, so
+// there's no concrete type to monomorphize it to.
+fn monomorphize(expr: Expr) -> Result<Expr, CodegenError> {
+    match expr {
+        Expr::TypeApp { arg, abs, .. } => {
+            // Peel a curried chain `e [T1] [T2] ...` down to its head and
+            // substitute each argument into the matching `TypeAbs` binder
+            // before recursing: recursing first would hit the bare-`TypeAbs`
+            // arm below and reject a binder as "unresolved" even though an
+            // argument of this chain is about to resolve it.
+            let mut args = vec![arg];
+            let mut head = *abs;
+            while let Expr::TypeApp { arg, abs, .. } = head {
+                args.push(arg);
+                head = *abs;
+            }
+            for arg in args.into_iter().rev() {
+                if let Expr::TypeAbs { param, body, .. } = head {
+                    head = substitute_expr_type(*body, &param, &arg);
+                }
+            }
+            monomorphize(head)
+        }
+        Expr::TypeAbs { param, .. } => Err(CodegenError::UnresolvedPolymorphism { param }),
+        Expr::Abs {
+            param,
+            param_type,
+            body,
+            span,
+        } => Ok(Expr::Abs {
+            param,
+            param_type,
+            body: Box::new(monomorphize(*body)?),
+            span,
+        }),
+        Expr::App { arg, abs, span } => Ok(Expr::App {
+            arg: Box::new(monomorphize(*arg)?),
+            abs: Box::new(monomorphize(*abs)?),
+            span,
+        }),
+        Expr::Let {
+            name,
+            bound,
+            body,
+            span,
+        } => Ok(Expr::Let {
+            name,
+            bound: Box::new(monomorphize(*bound)?),
+            body: Box::new(monomorphize(*body)?),
+            span,
+        }),
+        other => Ok(other),
+    }
+}
+
+// Replaces a type variable with a concrete type throughout an `Expr`'s
+// annotations, mirroring what `replace_type` does for a bare `Type`.
+fn substitute_expr_type(expr: Expr, param: &str, arg: &Type) -> Expr {
+    match expr {
+        Expr::Abs {
+            param: name,
+            param_type,
+            body,
+            span,
+        } => Expr::Abs {
+            param: name,
+            param_type: param_type
+                .map(|type_| replace_type(&type_, param.to_string(), arg.clone())),
+            body: Box::new(substitute_expr_type(*body, param, arg)),
+            span,
+        },
+        Expr::App {
+            arg: call_arg,
+            abs,
+            span,
+        } => Expr::App {
+            arg: Box::new(substitute_expr_type(*call_arg, param, arg)),
+            abs: Box::new(substitute_expr_type(*abs, param, arg)),
+            span,
+        },
+        Expr::TypeAbs {
+            param: inner_param,
+            body,
+            span,
+        } => Expr::TypeAbs {
+            param: inner_param,
+            body: Box::new(substitute_expr_type(*body, param, arg)),
+            span,
+        },
+        Expr::TypeApp {
+            arg: type_arg,
+            abs,
+            span,
+        } => Expr::TypeApp {
+            arg: replace_type(&type_arg, param.to_string(), arg.clone()),
+            abs: Box::new(substitute_expr_type(*abs, param, arg)),
+            span,
+        },
+        Expr::Let {
+            name,
+            bound,
+            body,
+            span,
+        } => Expr::Let {
+            name,
+            bound: Box::new(substitute_expr_type(*bound, param, arg)),
+            body: Box::new(substitute_expr_type(*body, param, arg)),
+            span,
+        },
+        other => other,
+    }
+}
+
+// Collects the names an `Expr` refers to that aren't bound within it,
+// in the order they're first seen. Used to decide what a closure needs to
+// capture into its environment struct.
+fn free_vars(expr: &Expr, bound: &mut Vec<String>, out: &mut Vec<String>) {
+    match expr {
+        Expr::Var { name, .. } => {
+            if !bound.contains(name) && !out.contains(name) {
+                out.push(name.clone());
+            }
+        }
+        Expr::Abs { param, body, .. } => {
+            bound.push(param.clone());
+            free_vars(body, bound, out);
+            bound.pop();
+        }
+        Expr::TypeAbs { body, .. } => free_vars(body, bound, out),
+        Expr::TypeApp { abs, .. } => free_vars(abs, bound, out),
+        Expr::App { arg, abs, .. } => {
+            free_vars(arg, bound, out);
+            free_vars(abs, bound, out);
+        }
+        Expr::Let {
+            name,
+            bound: let_bound,
+            body,
+            ..
+        } => {
+            free_vars(let_bound, bound, out);
+            bound.push(name.clone());
+            free_vars(body, bound, out);
+            bound.pop();
+        }
+        Expr::Int { .. } | Expr::Str { .. } => {}
+    }
+}
+
+// A value produced while lowering an `Expr` to IR: either a ground scalar,
+// or a closure represented as its generated function paired with a
+// pointer to its captured environment struct.
+#[derive(Clone, Copy)]
+enum CgValue<'ctx> {
+    Int(IntValue<'ctx>),
+    Str(PointerValue<'ctx>),
+    Closure {
+        function: FunctionValue<'ctx>,
+        env: PointerValue<'ctx>,
+    },
+}
+
+fn basic_type_of<'ctx>(value: &CgValue<'ctx>) -> BasicTypeEnum<'ctx> {
+    match value {
+        CgValue::Int(int) => int.get_type().into(),
+        CgValue::Str(ptr) => ptr.get_type().into(),
+        CgValue::Closure { env, .. } => env.get_type().into(),
+    }
+}
+
+fn to_basic_value<'ctx>(value: &CgValue<'ctx>) -> BasicValueEnum<'ctx> {
+    match value {
+        CgValue::Int(int) => (*int).into(),
+        CgValue::Str(ptr) => (*ptr).into(),
+        CgValue::Closure { env, .. } => (*env).into(),
+    }
+}
+
+struct Codegen<'ctx> {
+    context: &'ctx Context,
+    module: Module<'ctx>,
+    builder: Builder<'ctx>,
+    next_closure: u64,
+}
+
+impl<'ctx> Codegen<'ctx> {
+    fn lower(
+        &mut self,
+        expr: &Expr,
+        scope: &NativeMap<String, CgValue<'ctx>>,
+    ) -> Result<CgValue<'ctx>, CodegenError> {
+        match expr {
+            Expr::Int { value, .. } => Ok(CgValue::Int(
+                self.context.i64_type().const_int(*value as u64, true),
+            )),
+            Expr::Str { value, .. } => {
+                let global = self
+                    .builder
+                    .build_global_string_ptr(value, "str")
+                    .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+                Ok(CgValue::Str(global.as_pointer_value()))
+            }
+            Expr::Var { name, .. } => scope.get(name).copied().ok_or_else(|| {
+                CodegenError::Llvm(format!("unbound variable at codegen: {}", name))
+            }),
+            Expr::Abs {
+                param,
+                param_type,
+                body,
+                ..
+            } => match param_type {
+                Some(Type::Int) => self.lower_closure(param, body, scope),
+                other => Err(CodegenError::UnsupportedClosureParam {
+                    param_type: other.clone(),
+                }),
+            },
+            Expr::App { arg, abs, .. } => {
+                let arg_value = self.lower(arg, scope)?;
+                match self.lower(abs, scope)? {
+                    CgValue::Closure { function, env } => {
+                        let call = self
+                            .builder
+                            .build_call(
+                                function,
+                                &[env.into(), to_basic_value(&arg_value).into()],
+                                "call",
+                            )
+                            .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+
+                        match call.try_as_basic_value().left() {
+                            Some(BasicValueEnum::IntValue(int)) => Ok(CgValue::Int(int)),
+                            Some(BasicValueEnum::PointerValue(ptr)) => Ok(CgValue::Str(ptr)),
+                            _ => Err(CodegenError::Llvm(String::from(
+                                "closure call produced no usable value",
+                            ))),
+                        }
+                    }
+                    _ => Err(CodegenError::Llvm(String::from(
+                        "attempted to call a non-closure value",
+                    ))),
+                }
+            }
+            Expr::Let {
+                name, bound, body, ..
+            } => {
+                let bound_value = self.lower(bound, scope)?;
+                let mut scope = scope.clone();
+                scope.insert(name.clone(), bound_value);
+                self.lower(body, &scope)
+            }
+            Expr::TypeAbs { .. } | Expr::TypeApp { .. } => Err(CodegenError::Llvm(String::from(
+                "monomorphization should have removed every TypeAbs/TypeApp",
+            ))),
+        }
+    }
+
+    fn lower_closure(
+        &mut self,
+        param: &str,
+        body: &Expr,
+        scope: &NativeMap<String, CgValue<'ctx>>,
+    ) -> Result<CgValue<'ctx>, CodegenError> {
+        let mut already_bound = vec![param.to_string()];
+        let mut captured_names = Vec::new();
+        free_vars(body, &mut already_bound, &mut captured_names);
+
+        let captured: Vec<(String, CgValue<'ctx>)> = captured_names
+            .into_iter()
+            .map(|name| {
+                scope
+                    .get(&name)
+                    .copied()
+                    .map(|value| (name.clone(), value))
+                    .ok_or_else(|| CodegenError::Llvm(format!("unbound capture: {}", name)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let env_fields: Vec<BasicTypeEnum<'ctx>> = captured
+            .iter()
+            .map(|(_, value)| basic_type_of(value))
+            .collect();
+        let env_type = self.context.struct_type(&env_fields, false);
+
+        let env_alloca = self
+            .builder
+            .build_alloca(env_type, "env")
+            .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+        for (index, (_, value)) in captured.iter().enumerate() {
+            let field = self
+                .builder
+                .build_struct_gep(env_alloca, index as u32, "field")
+                .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+            self.builder
+                .build_store(field, to_basic_value(value))
+                .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+        }
+
+        // Typed (pre-opaque-pointer) LLVM needs the environment pointer's
+        // own type to carry its pointee (`env_type`) so `build_struct_gep`
+        // below can compute field offsets, rather than a generic pointer.
+        let env_ptr_type = env_type.ptr_type(AddressSpace::default());
+        let i64_type = self.context.i64_type();
+        let fn_type = i64_type.fn_type(&[env_ptr_type.into(), i64_type.into()], false);
+
+        let name = format!("closure_{}", self.next_closure);
+        self.next_closure += 1;
+        let function = self.module.add_function(&name, fn_type, None);
+
+        let caller_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+
+        let mut inner_scope = NativeMap::new();
+        let env_param = function
+            .get_nth_param(0)
+            .expect("closure function always takes an environment pointer")
+            .into_pointer_value();
+        for (index, (name, _value)) in captured.iter().enumerate() {
+            let field = self
+                .builder
+                .build_struct_gep(env_param, index as u32, "capture")
+                .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+            let loaded = self
+                .builder
+                .build_load(field, "loaded")
+                .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+            let loaded = match loaded {
+                BasicValueEnum::IntValue(int) => CgValue::Int(int),
+                BasicValueEnum::PointerValue(ptr) => CgValue::Str(ptr),
+                _ => return Err(CodegenError::Llvm(String::from("unsupported capture type"))),
+            };
+            inner_scope.insert(name.clone(), loaded);
+        }
+        let param_value = function
+            .get_nth_param(1)
+            .expect("closure function always takes its argument")
+            .into_int_value();
+        inner_scope.insert(param.to_string(), CgValue::Int(param_value));
+
+        let result = self.lower(body, &inner_scope)?;
+        let CgValue::Int(_) = result else {
+            return Err(CodegenError::UnsupportedClosureReturn);
+        };
+        self.builder
+            .build_return(Some(&to_basic_value(&result)))
+            .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+
+        if let Some(block) = caller_block {
+            self.builder.position_at_end(block);
+        }
+
+        Ok(CgValue::Closure {
+            function,
+            env: env_alloca,
+        })
+    }
+}
+
+// Type-checks, monomorphizes, and lowers `expr` to LLVM IR, then emits a
+// native object file at `out` containing a `main` that returns the
+// evaluated `Int`.
+pub fn compile(expr: Expr, out: &Path) -> Result<(), CodegenError> {
+    let type_ = infer(expr.clone(), TypeContext::new()).map_err(CodegenError::Typecheck)?;
+    if type_ != Type::Int {
+        return Err(CodegenError::NotCompilable { found: type_ });
+    }
+
+    let expr = monomorphize(expr)?;
+
+    let context = Context::create();
+    let module = context.create_module("system_f");
+    let builder = context.create_builder();
+
+    let i64_type = context.i64_type();
+    let main_fn = module.add_function("main", i64_type.fn_type(&[], false), None);
+    let entry = context.append_basic_block(main_fn, "entry");
+    builder.position_at_end(entry);
+
+    let mut codegen = Codegen {
+        context: &context,
+        module,
+        builder,
+        next_closure: 0,
+    };
+
+    let result = match codegen.lower(&expr, &NativeMap::new())? {
+        CgValue::Int(int) => int,
+        _ => {
+            return Err(CodegenError::Llvm(String::from(
+                "top-level expression did not evaluate to an Int",
+            )))
+        }
+    };
+
+    codegen
+        .builder
+        .build_return(Some(&result))
+        .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+
+    Target::initialize_native(&InitializationConfig::default())
+        .map_err(|error| CodegenError::Llvm(error.to_string()))?;
+    let triple = TargetMachine::get_default_triple();
+    let target =
+        Target::from_triple(&triple).map_err(|error| CodegenError::Llvm(error.to_string()))?;
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| CodegenError::Llvm(String::from("failed to create a target machine")))?;
+
+    machine
+        .write_to_file(&codegen.module, FileType::Object, out)
+        .map_err(|error| CodegenError::Llvm(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_monomorphizes_an_applied_type_abstraction() {
+        // (/\a. \x:a. x) [Int]  ->  \x:Int. x
+        let ast = Expr::TypeApp {
+            arg: Type::Int,
+            abs: Box::new(Expr::TypeAbs {
+                param: String::from("a"),
+                body: Box::new(Expr::Abs {
+                    param: String::from("x"),
+                    param_type: Some(Type::Var(String::from("a"))),
+                    body: Box::new(Expr::Var {
+                        name: String::from("x"),
+                        span: None,
+                    }),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let monomorphized = monomorphize(ast).unwrap();
+        match monomorphized {
+            Expr::Abs { param_type, .. } => assert_eq!(param_type, Some(Type::Int)),
+            other => panic!("expected an Abs, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_type_abstraction_left_unapplied() {
+        let ast = Expr::TypeAbs {
+            param: String::from("a"),
+            body: Box::new(Expr::Int {
+                value: 0,
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            monomorphize(ast),
+            Err(CodegenError::UnresolvedPolymorphism { .. })
+        ));
+    }
+
+    #[test]
+    fn it_collects_the_free_variables_of_a_closure_body() {
+        // \x. y x, with `y` free and `x` bound by the lambda
+        let body = Expr::App {
+            arg: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            abs: Box::new(Expr::Var {
+                name: String::from("y"),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let mut bound = vec![String::from("x")];
+        let mut out = Vec::new();
+        free_vars(&body, &mut bound, &mut out);
+
+        assert_eq!(out, vec![String::from("y")]);
+    }
+}
@@ -1,12 +1,23 @@
-use std::fmt::{format, Display};
+// This binary's `main` is just a smoke-test stub; the real surface
+// (`infer`/`eval`/`codegen::compile`/...) is a library exercised from
+// tests and meant to be driven interactively, so most of it is unused
+// from `main`'s point of view.
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::fmt::Display;
 
 use im::hashmap::HashMap;
 
+mod codegen;
+mod parser;
+
 #[derive(Debug, Clone, PartialEq)]
 enum Type {
     Closure { param: Box<Type>, body: Box<Type> },
     Forall { param: String, body: Box<Type> },
     Var(String),
+    Unknown(u64),
     Int,
     Str,
 }
@@ -16,9 +27,13 @@ impl Display for Type {
         use Type::*;
 
         let type_ = match self {
-            Closure { param, body } => format!("{} -> {}", param, body),
-            Forall { param, body } => format!("{} -> {}", param, body),
-            Var(var) => format!("Var({})", var),
+            Closure { param, body } => match **param {
+                Closure { .. } => format!("({}) -> {}", param, body),
+                _ => format!("{} -> {}", param, body),
+            },
+            Forall { param, body } => format!("forall {}. {}", param, body),
+            Var(var) => var.clone(),
+            Unknown(id) => format!("?{}", id),
             Int => String::from("Int"),
             Str => String::from("Str"),
         };
@@ -27,27 +42,70 @@ impl Display for Type {
     }
 }
 
+// A byte-offset range into the source text an `Expr` node was parsed from.
+// `None` for programmatically-built ASTs that never had source text.
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 enum Expr {
-    Int(i64),
-    Var(String),
+    Int {
+        value: i64,
+        span: Option<Span>,
+    },
+    Str {
+        value: String,
+        span: Option<Span>,
+    },
+    Var {
+        name: String,
+        span: Option<Span>,
+    },
     Abs {
         param: String,
-        param_type: Type,
+        param_type: Option<Type>,
         body: Box<Expr>,
+        span: Option<Span>,
     },
     TypeAbs {
         param: String,
         body: Box<Expr>,
+        span: Option<Span>,
     },
     TypeApp {
         arg: Type,
         abs: Box<Expr>,
+        span: Option<Span>,
     },
     App {
         arg: Box<Expr>,
         abs: Box<Expr>,
+        span: Option<Span>,
     },
+    Let {
+        name: String,
+        bound: Box<Expr>,
+        body: Box<Expr>,
+        span: Option<Span>,
+    },
+}
+
+fn expr_span(expr: &Expr) -> Option<Span> {
+    use Expr::*;
+
+    match expr {
+        Int { span, .. }
+        | Str { span, .. }
+        | Var { span, .. }
+        | Abs { span, .. }
+        | TypeAbs { span, .. }
+        | TypeApp { span, .. }
+        | App { span, .. }
+        | Let { span, .. } => span.clone(),
+    }
 }
 
 impl Display for Expr {
@@ -55,16 +113,27 @@ impl Display for Expr {
         use Expr::*;
 
         let expr = match self {
-            Int(int) => int.to_string(),
-            Var(name) => name.clone(),
+            Int { value, .. } => value.to_string(),
+            Str { value, .. } => format!("\"{}\"", value),
+            Var { name, .. } => name.clone(),
             Abs {
                 param,
-                param_type,
+                param_type: Some(param_type),
                 body,
+                ..
             } => format!("(λ{}:{}.{})", param, param_type, body),
-            TypeAbs { param, body } => format!("(Λ{}.{})", param, body),
-            TypeApp { arg, abs } => format!("({} {})", abs, arg),
-            App { arg, abs } => format!("({} {})", abs, arg),
+            Abs {
+                param,
+                param_type: None,
+                body,
+                ..
+            } => format!("(λ{}.{})", param, body),
+            TypeAbs { param, body, .. } => format!("(Λ{}.{})", param, body),
+            TypeApp { arg, abs, .. } => format!("({} [{}])", abs, arg),
+            App { arg, abs, .. } => format!("({} {})", abs, arg),
+            Let {
+                name, bound, body, ..
+            } => format!("(let {} = {} in {})", name, bound, body),
         };
 
         write!(f, "{}", expr)
@@ -73,6 +142,64 @@ impl Display for Expr {
 
 type TypeContext = HashMap<String, Type>;
 
+// The set of type-variable names currently bound by an enclosing `Forall`,
+// i.e. the ones a `Type::Var` is allowed to refer to.
+type TypeVarContext = HashSet<String>;
+
+// Rejects a `Var` that isn't bound by any enclosing `Forall` in `scope`.
+// Run on every annotation a user writes by hand (`Abs::param_type`,
+// `TypeApp::arg`) so a typo'd or forgotten type variable is caught at the
+// point it's written rather than surfacing as a confusing unify failure.
+fn well_formed(type_: &Type, scope: &TypeVarContext) -> Result<(), Error> {
+    use Type::*;
+
+    match type_ {
+        Var(name) if !scope.contains(name) => Err(Error::UnboundTypeVariable {
+            name: name.clone(),
+            span: None,
+        }),
+        Var(_) | Unknown(_) | Int | Str => Ok(()),
+        Closure { param, body } => {
+            well_formed(param, scope)?;
+            well_formed(body, scope)
+        }
+        Forall { param, body } => {
+            let mut scope = scope.clone();
+            scope.insert(param.clone());
+            well_formed(body, &scope)
+        }
+    }
+}
+
+fn free_vars(type_: &Type, out: &mut HashSet<String>) {
+    match type_ {
+        Type::Var(name) => {
+            out.insert(name.clone());
+        }
+        Type::Closure { param, body } => {
+            free_vars(param, out);
+            free_vars(body, out);
+        }
+        Type::Forall { param, body } => {
+            let mut inner = HashSet::new();
+            free_vars(body, &mut inner);
+            inner.remove(param);
+            out.extend(inner);
+        }
+        Type::Unknown(_) | Type::Int | Type::Str => {}
+    }
+}
+
+// Picks a name derived from `base` that doesn't appear in `avoid`, by
+// appending `'` until it's unique.
+fn fresh_type_var(base: &str, avoid: &HashSet<String>) -> String {
+    let mut name = format!("{}'", base);
+    while avoid.contains(&name) {
+        name.push('\'');
+    }
+    name
+}
+
 fn replace_type(type_: &Type, from: String, to: Type) -> Type {
     use Type::*;
 
@@ -86,72 +213,501 @@ fn replace_type(type_: &Type, from: String, to: Type) -> Type {
                 body: Box::new(body),
             }
         }
-        Forall { param, body } => match param == &from {
-            true => Forall {
-                param: param.clone(),
-                body: body.clone(),
-            },
-            false => Forall {
-                param: param.clone(),
-                body: Box::new(replace_type(&*body, from, to)),
-            },
+        Forall { param, body } if param == &from => Forall {
+            param: param.clone(),
+            body: body.clone(),
         },
+        // If `to` mentions the same name as this `Forall`'s bound
+        // variable, substituting straight through would capture it (e.g.
+        // replacing `b` with `a` inside `forall a. b -> a` must not turn
+        // into `forall a. a -> a`). Alpha-rename the binder to a fresh
+        // name first so the substitution can't capture anything.
+        Forall { param, body } => {
+            let mut to_free = HashSet::new();
+            free_vars(&to, &mut to_free);
+
+            if to_free.contains(param) {
+                let mut avoid = to_free;
+                free_vars(body, &mut avoid);
+                let fresh = fresh_type_var(param, &avoid);
+                let renamed_body = replace_type(body, param.clone(), Var(fresh.clone()));
+
+                Forall {
+                    param: fresh,
+                    body: Box::new(replace_type(&renamed_body, from, to)),
+                }
+            } else {
+                Forall {
+                    param: param.clone(),
+                    body: Box::new(replace_type(body, from, to)),
+                }
+            }
+        }
         Var(var) => match var == &from {
             true => to,
             false => type_.clone(),
         },
+        Unknown(id) => Unknown(*id),
         Int => Int,
         Str => Str,
     }
 }
 
-fn infer(expr: Expr, context: TypeContext) -> Type {
+// Algorithm-W bookkeeping: a counter for fresh metavariables plus the
+// substitution they've been resolved to so far. Threaded by `&mut` through
+// `infer_with` instead of returned, since every nested call needs to see
+// bindings made by its siblings (e.g. the argument side of an `App` must be
+// visible when unifying the function side).
+struct Infer {
+    next_unknown: u64,
+    substitution: HashMap<u64, Type>,
+}
+
+impl Infer {
+    fn new() -> Self {
+        Infer {
+            next_unknown: 0,
+            substitution: HashMap::new(),
+        }
+    }
+
+    fn fresh(&mut self) -> Type {
+        let id = self.next_unknown;
+        self.next_unknown += 1;
+        Type::Unknown(id)
+    }
+}
+
+fn apply_substitution(type_: &Type, substitution: &HashMap<u64, Type>) -> Type {
+    use Type::*;
+
+    match type_ {
+        Unknown(id) => match substitution.get(id) {
+            Some(resolved) => apply_substitution(resolved, substitution),
+            None => type_.clone(),
+        },
+        Closure { param, body } => Closure {
+            param: Box::new(apply_substitution(param, substitution)),
+            body: Box::new(apply_substitution(body, substitution)),
+        },
+        Forall { param, body } => Forall {
+            param: param.clone(),
+            body: Box::new(apply_substitution(body, substitution)),
+        },
+        Var(_) | Int | Str => type_.clone(),
+    }
+}
+
+// Recursively resolves every metavariable in `type_` against the
+// substitution collected so far.
+fn zonk(type_: &Type, infer_state: &Infer) -> Type {
+    apply_substitution(type_, &infer_state.substitution)
+}
+
+fn occurs(id: u64, type_: &Type, substitution: &HashMap<u64, Type>) -> bool {
+    use Type::*;
+
+    match apply_substitution(type_, substitution) {
+        Unknown(other) => other == id,
+        Closure { param, body } => {
+            occurs(id, &param, substitution) || occurs(id, &body, substitution)
+        }
+        Forall { body, .. } => occurs(id, &body, substitution),
+        Var(_) | Int | Str => false,
+    }
+}
+
+// All failure modes of `infer` and `eval`, each carrying the source span (if
+// any) of the subterm that caused it.
+#[derive(Debug, Clone, PartialEq)]
+enum Error {
+    UnboundVariable {
+        name: String,
+        span: Option<Span>,
+    },
+    UnboundTypeVariable {
+        name: String,
+        span: Option<Span>,
+    },
+    TypeMismatch {
+        expected: Type,
+        actual: Type,
+        span: Option<Span>,
+    },
+    NotAClosure {
+        found: Type,
+        span: Option<Span>,
+    },
+    InvalidTypeApplication {
+        found: Type,
+        span: Option<Span>,
+    },
+    OccursCheck {
+        var: u64,
+        found: Type,
+        span: Option<Span>,
+    },
+    // Boxed since `Value` (with its `Closure`/`Forall` variants carrying a
+    // whole `ValueContext`) is much larger than `Error`'s other variants,
+    // and `Error` is returned by value from every recursive `infer`/`eval`
+    // call.
+    NotApplicable {
+        found: Box<Value>,
+        span: Option<Span>,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Error::*;
+
+        let message = match self {
+            UnboundVariable { name, .. } => format!("unbound variable: {}", name),
+            UnboundTypeVariable { name, .. } => format!("unbound type variable: {}", name),
+            TypeMismatch {
+                expected, actual, ..
+            } => format!("expecting type {}. {} given", expected, actual),
+            NotAClosure { found, .. } => format!("type {} cannot be used as a closure", found),
+            InvalidTypeApplication { found, .. } => {
+                format!("cannot apply a type to {}", found)
+            }
+            OccursCheck { var, found, .. } => {
+                format!("occurs check failed: ?{} occurs in {}", var, found)
+            }
+            NotApplicable { found, .. } => format!("value {} cannot be applied", found),
+        };
+
+        f.write_str(&message)
+    }
+}
+
+impl Error {
+    fn span(&self) -> Option<&Span> {
+        use Error::*;
+
+        match self {
+            UnboundVariable { span, .. }
+            | UnboundTypeVariable { span, .. }
+            | TypeMismatch { span, .. }
+            | NotAClosure { span, .. }
+            | InvalidTypeApplication { span, .. }
+            | OccursCheck { span, .. }
+            | NotApplicable { span, .. } => span.as_ref(),
+        }
+    }
+
+    // Renders the error message together with a caret line underlining the
+    // span of the offending subterm in `source`.
+    fn render(&self, source: &str) -> String {
+        match self.span() {
+            Some(span) => format!(
+                "{}\n{}\n{}{}",
+                self,
+                source,
+                " ".repeat(span.start),
+                "^".repeat((span.end - span.start).max(1)),
+            ),
+            None => self.to_string(),
+        }
+    }
+}
+
+// Fills in `span` on an `Error` that didn't originate at a point with source
+// information of its own (e.g. a failure raised deep inside `unify`), so the
+// caller that does have the surrounding `Expr`'s span can attach it.
+fn with_span(error: Error, span: Option<Span>) -> Error {
+    use Error::*;
+
+    match error {
+        UnboundVariable { name, span: None } => UnboundVariable { name, span },
+        UnboundTypeVariable { name, span: None } => UnboundTypeVariable { name, span },
+        TypeMismatch {
+            expected,
+            actual,
+            span: None,
+        } => TypeMismatch {
+            expected,
+            actual,
+            span,
+        },
+        NotAClosure { found, span: None } => NotAClosure { found, span },
+        InvalidTypeApplication { found, span: None } => InvalidTypeApplication { found, span },
+        OccursCheck {
+            var,
+            found,
+            span: None,
+        } => OccursCheck { var, found, span },
+        NotApplicable { found, span: None } => NotApplicable { found, span },
+        other => other,
+    }
+}
+
+fn bind(id: u64, type_: Type, infer_state: &mut Infer) -> Result<(), Error> {
+    if let Type::Unknown(other) = type_ {
+        if other == id {
+            return Ok(());
+        }
+    }
+
+    if occurs(id, &type_, &infer_state.substitution) {
+        return Err(Error::OccursCheck {
+            var: id,
+            found: type_,
+            span: None,
+        });
+    }
+
+    infer_state.substitution = infer_state.substitution.update(id, type_);
+    Ok(())
+}
+
+fn unify(a: &Type, b: &Type, infer_state: &mut Infer) -> Result<(), Error> {
+    use Type::*;
+
+    let a = apply_substitution(a, &infer_state.substitution);
+    let b = apply_substitution(b, &infer_state.substitution);
+
+    match (&a, &b) {
+        (Unknown(id), _) => bind(*id, b, infer_state),
+        (_, Unknown(id)) => bind(*id, a, infer_state),
+        (
+            Closure {
+                param: p1,
+                body: b1,
+            },
+            Closure {
+                param: p2,
+                body: b2,
+            },
+        ) => {
+            unify(p1, p2, infer_state)?;
+            unify(b1, b2, infer_state)
+        }
+        (
+            Forall {
+                param: p1,
+                body: b1,
+            },
+            Forall {
+                param: p2,
+                body: b2,
+            },
+        ) => {
+            // Alpha-equivalence, not literal name equality: rename `b2`'s
+            // bound variable to `p1`'s before comparing bodies, so e.g.
+            // `forall a. a -> a` and `forall b. b -> b` unify.
+            let b2 = replace_type(b2, p2.clone(), Var(p1.clone()));
+            unify(b1, &b2, infer_state)
+        }
+        (Var(v1), Var(v2)) if v1 == v2 => Ok(()),
+        (Int, Int) => Ok(()),
+        (Str, Str) => Ok(()),
+        _ => Err(Error::TypeMismatch {
+            expected: a,
+            actual: b,
+            span: None,
+        }),
+    }
+}
+
+fn free_metavars(type_: &Type, out: &mut HashSet<u64>) {
+    match type_ {
+        Type::Unknown(id) => {
+            out.insert(*id);
+        }
+        Type::Closure { param, body } => {
+            free_metavars(param, out);
+            free_metavars(body, out);
+        }
+        Type::Forall { body, .. } => free_metavars(body, out),
+        Type::Var(_) | Type::Int | Type::Str => {}
+    }
+}
+
+// Replaces every occurrence of the metavariable `id` with the bound type
+// variable `name`. Unlike `replace_type`, this substitutes an `Unknown`
+// rather than a `Var`, which is all that's needed here: `name` is freshly
+// generated per generalized metavariable, so it can never already be bound
+// by an enclosing `Forall`.
+fn generalize_var(type_: &Type, id: u64, name: &str) -> Type {
+    match type_ {
+        Type::Unknown(other) if *other == id => Type::Var(name.to_string()),
+        Type::Closure { param, body } => Type::Closure {
+            param: Box::new(generalize_var(param, id, name)),
+            body: Box::new(generalize_var(body, id, name)),
+        },
+        Type::Forall { param, body } => Type::Forall {
+            param: param.clone(),
+            body: Box::new(generalize_var(body, id, name)),
+        },
+        Type::Unknown(_) | Type::Var(_) | Type::Int | Type::Str => type_.clone(),
+    }
+}
+
+// Generalizes `bound_type` into a `Forall` over every metavariable that's
+// free in it but not free in `context` (i.e. not already pinned down by an
+// enclosing scope). This is what makes `let` polymorphic without requiring
+// an explicit `TypeAbs`.
+fn generalize(bound_type: &Type, context: &TypeContext, infer_state: &Infer) -> Type {
+    let mut bound_free = HashSet::new();
+    free_metavars(bound_type, &mut bound_free);
+
+    let mut context_free = HashSet::new();
+    for (_, type_) in context.iter() {
+        free_metavars(&zonk(type_, infer_state), &mut context_free);
+    }
+
+    let mut to_generalize: Vec<u64> = bound_free.difference(&context_free).copied().collect();
+    to_generalize.sort();
+
+    let mut generalized = bound_type.clone();
+    for id in &to_generalize {
+        generalized = generalize_var(&generalized, *id, &format!("t{}", id));
+    }
+
+    for id in to_generalize.into_iter().rev() {
+        generalized = Type::Forall {
+            param: format!("t{}", id),
+            body: Box::new(generalized),
+        };
+    }
+
+    generalized
+}
+
+// Replaces each of a `Forall`'s bound variables with a fresh metavariable,
+// recovering a usable (monomorphic, for now) type at the use site.
+fn instantiate(type_: Type, infer_state: &mut Infer) -> Type {
+    match type_ {
+        Type::Forall { param, body } => {
+            let fresh = infer_state.fresh();
+            instantiate(replace_type(&body, param, fresh), infer_state)
+        }
+        other => other,
+    }
+}
+
+fn infer(expr: Expr, context: TypeContext) -> Result<Type, Error> {
+    let mut infer_state = Infer::new();
+    let type_ = infer_with(expr, context, &mut infer_state, &TypeVarContext::new())?;
+
+    Ok(zonk(&type_, &infer_state))
+}
+
+fn infer_with(
+    expr: Expr,
+    context: TypeContext,
+    infer_state: &mut Infer,
+    scope: &TypeVarContext,
+) -> Result<Type, Error> {
     match expr {
-        Expr::Var(var) => context
-            .get(&var)
-            .expect(&format! {"type error: unbound variable {var}"})
-            .clone(),
+        Expr::Var { name, span } => {
+            let type_ = context
+                .get(&name)
+                .cloned()
+                .ok_or_else(|| Error::UnboundVariable { name, span })?;
+
+            Ok(instantiate(type_, infer_state))
+        }
         Expr::Abs {
             param,
             param_type,
             body,
+            span,
         } => {
+            if let Some(param_type) = &param_type {
+                well_formed(param_type, scope).map_err(|error| with_span(error, span))?;
+            }
+
+            let param_type = param_type.unwrap_or_else(|| infer_state.fresh());
             let context = context.update(param, param_type.clone());
 
-            let body = infer(*body, context);
-            Type::Closure {
-                param: Box::new(param_type),
+            let body = infer_with(*body, context, infer_state, scope)?;
+            Ok(Type::Closure {
+                param: Box::new(zonk(&param_type, infer_state)),
                 body: Box::new(body),
-            }
+            })
         }
-        Expr::App { arg, abs } => {
-            let arg = infer(*arg, context.clone());
-
-            match infer(*abs, context) {
-                Type::Closure { param, body } => match *param == arg {
-                    true => *body,
-                    false => panic!("expecting type {}. {} given", (*param), arg),
-                },
-                typ => panic!("type {} cannot be used as a closure", typ),
+        Expr::App { arg, abs, span } => {
+            let arg_span = expr_span(&arg);
+            let abs_span = expr_span(&abs);
+
+            let arg_type = infer_with(*arg, context.clone(), infer_state, scope)?;
+            let fun_type = infer_with(*abs, context, infer_state, scope)?;
+            let fun_type = zonk(&fun_type, infer_state);
+
+            match fun_type {
+                Type::Closure { param, body } => {
+                    unify(&param, &arg_type, infer_state)
+                        .map_err(|error| with_span(error, arg_span.or(span)))?;
+
+                    Ok(zonk(&body, infer_state))
+                }
+                Type::Unknown(_) => {
+                    let result = infer_state.fresh();
+                    unify(
+                        &fun_type,
+                        &Type::Closure {
+                            param: Box::new(arg_type),
+                            body: Box::new(result.clone()),
+                        },
+                        infer_state,
+                    )
+                    .map_err(|error| with_span(error, arg_span.or(span)))?;
+
+                    Ok(zonk(&result, infer_state))
+                }
+                found => Err(Error::NotAClosure {
+                    found,
+                    span: abs_span.or(span),
+                }),
             }
         }
-        Expr::TypeAbs { param, body } => {
-            let body = infer(*body, context);
+        Expr::TypeAbs { param, body, .. } => {
+            let mut scope = scope.clone();
+            scope.insert(param.clone());
+
+            let body = infer_with(*body, context, infer_state, &scope)?;
 
-            Type::Forall {
+            Ok(Type::Forall {
                 param,
                 body: Box::new(body),
+            })
+        }
+        Expr::TypeApp { arg, abs, span } => {
+            let abs_span = expr_span(&abs);
+
+            well_formed(&arg, scope).map_err(|error| with_span(error, span.clone()))?;
+
+            match infer_with(*abs, context, infer_state, scope)? {
+                Type::Forall { param, body } => Ok(replace_type(&body, param, arg)),
+                found => Err(Error::InvalidTypeApplication {
+                    found,
+                    span: abs_span.or(span),
+                }),
             }
         }
-        Expr::TypeApp { arg, abs } => match infer(*abs.clone(), context) {
-            Type::Forall { param, body } => replace_type(&*body, param, arg),
-            type_ => panic!("cannot apply type {} to {}", abs, type_),
-        },
-        Expr::Int(_int) => Type::Int,
+        Expr::Let {
+            name, bound, body, ..
+        } => {
+            let bound_type = infer_with(*bound, context.clone(), infer_state, scope)?;
+            let bound_type = zonk(&bound_type, infer_state);
+            let generalized = generalize(&bound_type, &context, infer_state);
+
+            let context = context.update(name, generalized);
+            infer_with(*body, context, infer_state, scope)
+        }
+        Expr::Int { .. } => Ok(Type::Int),
+        Expr::Str { .. } => Ok(Type::Str),
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+// `Native`'s derived equality compares function pointers, which is only
+// ever used to compare a `Native` value against itself (never two
+// distinct natives expected to be interchangeable), so address identity
+// is exactly what we want here despite the lint's general warning.
+#[allow(unpredictable_function_pointer_comparisons)]
 enum Value {
     Closure {
         param: String,
@@ -163,6 +719,7 @@ enum Value {
         context: ValueContext,
     },
     Int(i64),
+    Str(String),
     Native(fn(Box<Value>) -> Box<Value>),
 }
 
@@ -178,6 +735,7 @@ impl Display for Value {
             } => format!("(Closure {} -> {} )", param, body),
             Forall { body, context: _ } => format!("(Forall {})", body),
             Int(int) => int.to_string(),
+            Str(str_) => str_.clone(),
             Native(_) => "(Native)".to_string(),
         };
 
@@ -187,55 +745,75 @@ impl Display for Value {
 
 type ValueContext = HashMap<String, Value>;
 
-fn eval(expr: Expr, context: ValueContext) -> Value {
+fn eval(expr: Expr, context: ValueContext) -> Result<Value, Error> {
     match expr {
-        Expr::Var(var) => context
-            .get(&var)
-            .expect(&format!("unbound variable: {}", var))
-            .clone(),
+        Expr::Var { name, span } => context
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| Error::UnboundVariable { name, span }),
         Expr::Abs {
             param,
             param_type: _,
             body,
-        } => Value::Closure {
+            ..
+        } => Ok(Value::Closure {
             param,
             body: *body,
             context,
-        },
-        Expr::App { arg, abs } => {
-            let arg = eval(*arg, context.clone());
+        }),
+        Expr::App { arg, abs, span } => {
+            let abs_span = expr_span(&abs);
+            let arg = eval(*arg, context.clone())?;
 
-            match eval(*abs, context) {
+            match eval(*abs, context)? {
                 Value::Closure {
                     param,
                     body,
                     context,
                 } => {
                     let context = context.update(param, arg);
-
                     eval(body, context)
                 }
-                Value::Native(native) => *(native(Box::new(arg))),
-                Value::Int(_value) => panic!(),
-                Value::Forall {
-                    body: _,
-                    context: _,
-                } => panic!(),
+                Value::Native(native) => Ok(*(native(Box::new(arg)))),
+                found => Err(Error::NotApplicable {
+                    found: Box::new(found),
+                    span: abs_span.or(span),
+                }),
             }
         }
         // the forall value is evaluated later. this is just no-op
-        Expr::TypeAbs { param: _, body } => Value::Forall {
+        Expr::TypeAbs { param: _, body, .. } => Ok(Value::Forall {
             body: *body,
             context,
-        },
-        Expr::TypeApp { arg: _, abs } => match eval(*abs, context) {
-            Value::Forall { body, context } => eval(body, context),
-            _ => panic!("invalid type application"),
-        },
-        Expr::Int(int) => Value::Int(int),
+        }),
+        Expr::TypeApp { arg: _, abs, span } => {
+            let abs_span = expr_span(&abs);
+
+            match eval(*abs, context)? {
+                Value::Forall { body, context } => eval(body, context),
+                found => Err(Error::NotApplicable {
+                    found: Box::new(found),
+                    span: abs_span.or(span),
+                }),
+            }
+        }
+        Expr::Let {
+            name, bound, body, ..
+        } => {
+            let bound = eval(*bound, context.clone())?;
+            let context = context.update(name, bound);
+
+            eval(*body, context)
+        }
+        Expr::Int { value, .. } => Ok(Value::Int(value)),
+        Expr::Str { value, .. } => Ok(Value::Str(value)),
     }
 }
 
+fn main() {
+    println!("run `cargo test` to see if it works");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,11 +822,15 @@ mod tests {
     fn it_infers_identity_function() {
         let ast = Expr::Abs {
             param: String::from("x"),
-            param_type: Type::Int,
-            body: Box::new(Expr::Var(String::from("x"))),
+            param_type: Some(Type::Int),
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
         };
 
-        let infered_type = infer(ast.clone(), TypeContext::new());
+        let infered_type = infer(ast.clone(), TypeContext::new()).unwrap();
         let expected_type = Type::Closure {
             param: Box::new(Type::Int),
             body: Box::new(Type::Int),
@@ -257,10 +839,14 @@ mod tests {
         assert_eq!(infered_type, expected_type);
 
         let ast = Expr::App {
-            arg: Box::new(Expr::Int(4)),
+            arg: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
             abs: Box::new(ast),
+            span: None,
         };
-        let infered_type = infer(ast, TypeContext::new());
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
         let expected_type = Type::Int;
 
         assert_eq!(infered_type, expected_type);
@@ -270,25 +856,36 @@ mod tests {
     fn it_evals_identity_function() {
         let ast = Expr::Abs {
             param: String::from("x"),
-            param_type: Type::Str,
-            body: Box::new(Expr::Var(String::from("x"))),
+            param_type: Some(Type::Str),
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
         };
 
-        let evaluated_value = eval(ast.clone(), ValueContext::new());
+        let evaluated_value = eval(ast.clone(), ValueContext::new()).unwrap();
         let expected_value = Value::Closure {
             param: String::from("x"),
-            body: Expr::Var(String::from("x")),
+            body: Expr::Var {
+                name: String::from("x"),
+                span: None,
+            },
             context: ValueContext::new(),
         };
 
         assert_eq!(evaluated_value, expected_value);
 
         let ast = Expr::App {
-            arg: Box::new(Expr::Int(4)),
+            arg: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
             abs: Box::new(ast),
+            span: None,
         };
 
-        let evaluated_value = eval(ast, ValueContext::new());
+        let evaluated_value = eval(ast, ValueContext::new()).unwrap();
         let expected_value = Value::Int(4);
 
         assert_eq!(evaluated_value, expected_value);
@@ -300,12 +897,17 @@ mod tests {
             param: String::from("a"),
             body: Box::new(Expr::Abs {
                 param: String::from("x"),
-                param_type: Type::Var(String::from("a")),
-                body: Box::new(Expr::Var(String::from("x"))),
+                param_type: Some(Type::Var(String::from("a"))),
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
             }),
+            span: None,
         };
 
-        let infered_type = infer(ast.clone(), TypeContext::new());
+        let infered_type = infer(ast.clone(), TypeContext::new()).unwrap();
         let expected_type = Type::Forall {
             param: String::from("a"),
             body: Box::new(Type::Closure {
@@ -319,9 +921,10 @@ mod tests {
         let ast = Expr::TypeApp {
             arg: Type::Int,
             abs: Box::new(ast),
+            span: None,
         };
 
-        let infered_type = infer(ast, TypeContext::new());
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
         let expected_type = Type::Closure {
             param: Box::new(Type::Int),
             body: Box::new(Type::Int),
@@ -336,17 +939,26 @@ mod tests {
             param: String::from("a"),
             body: Box::new(Expr::Abs {
                 param: String::from("x"),
-                param_type: Type::Var(String::from("a")),
-                body: Box::new(Expr::Var(String::from("x"))),
+                param_type: Some(Type::Var(String::from("a"))),
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
             }),
+            span: None,
         };
 
-        let evaluated_value = eval(ast.clone(), ValueContext::new());
+        let evaluated_value = eval(ast.clone(), ValueContext::new()).unwrap();
         let expected_value = Value::Forall {
             body: Expr::Abs {
                 param: String::from("x"),
-                param_type: Type::Var(String::from("a")),
-                body: Box::new(Expr::Var(String::from("x"))),
+                param_type: Some(Type::Var(String::from("a"))),
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
             },
             context: ValueContext::new(),
         };
@@ -356,19 +968,415 @@ mod tests {
         let ast = Expr::TypeApp {
             arg: Type::Int,
             abs: Box::new(ast),
+            span: None,
         };
 
-        let evaluated_value = eval(ast, ValueContext::new());
+        let evaluated_value = eval(ast, ValueContext::new()).unwrap();
         let expected_value = Value::Closure {
             param: String::from("x"),
-            body: Expr::Var(String::from("x")),
+            body: Expr::Var {
+                name: String::from("x"),
+                span: None,
+            },
             context: ValueContext::new(),
         };
 
         assert_eq!(evaluated_value, expected_value);
     }
-}
 
-fn main() {
-    println!("run `cargo test` to see if it works");
+    #[test]
+    fn it_infers_unannotated_identity_function_via_unification() {
+        let ast = Expr::Abs {
+            param: String::from("x"),
+            param_type: None,
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let infered_type = infer(ast.clone(), TypeContext::new()).unwrap();
+        match infered_type {
+            Type::Closure { param, body } => assert_eq!(param, body),
+            other => panic!("expected a closure type, got {}", other),
+        }
+
+        let ast = Expr::App {
+            arg: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
+            abs: Box::new(ast),
+            span: None,
+        };
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
+
+        assert_eq!(infered_type, Type::Int);
+    }
+
+    #[test]
+    fn it_rejects_self_application_via_occurs_check() {
+        let ast = Expr::Abs {
+            param: String::from("x"),
+            param_type: None,
+            body: Box::new(Expr::App {
+                arg: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                abs: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            infer(ast, TypeContext::new()),
+            Err(Error::OccursCheck { .. })
+        ));
+    }
+
+    #[test]
+    fn it_unifies_foralls_up_to_alpha_equivalence() {
+        // (\f:(forall b. b -> b). f) (/\a. \x:a. x)
+        let ast = Expr::App {
+            arg: Box::new(Expr::TypeAbs {
+                param: String::from("a"),
+                body: Box::new(Expr::Abs {
+                    param: String::from("x"),
+                    param_type: Some(Type::Var(String::from("a"))),
+                    body: Box::new(Expr::Var {
+                        name: String::from("x"),
+                        span: None,
+                    }),
+                    span: None,
+                }),
+                span: None,
+            }),
+            abs: Box::new(Expr::Abs {
+                param: String::from("f"),
+                param_type: Some(Type::Forall {
+                    param: String::from("b"),
+                    body: Box::new(Type::Closure {
+                        param: Box::new(Type::Var(String::from("b"))),
+                        body: Box::new(Type::Var(String::from("b"))),
+                    }),
+                }),
+                body: Box::new(Expr::Var {
+                    name: String::from("f"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        // Used to fail with a `TypeMismatch` between `Forall{b,...}` and
+        // `Forall{a,...}` even though they're alpha-equivalent; `f`'s
+        // returned type stays an unconstrained (monomorphic) closure since
+        // its only use is being returned, unannotated, as-is.
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
+        match infered_type {
+            Type::Closure { param, body } => assert_eq!(param, body),
+            other => panic!("expected a closure type, got {}", other),
+        }
+    }
+
+    #[test]
+    fn it_generalizes_a_let_bound_identity_function() {
+        // `generalize` is what actually drives let-polymorphism: check its
+        // output shape directly, since any use of the let-bound name as a
+        // `Var` immediately instantiates the `Forall` back away (by
+        // design, so each use can be specialized independently).
+        let ast = Expr::Abs {
+            param: String::from("x"),
+            param_type: None,
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let mut infer_state = Infer::new();
+        let bound_type = infer_with(
+            ast,
+            TypeContext::new(),
+            &mut infer_state,
+            &TypeVarContext::new(),
+        )
+        .unwrap();
+        let bound_type = zonk(&bound_type, &infer_state);
+        let generalized = generalize(&bound_type, &TypeContext::new(), &infer_state);
+
+        match generalized {
+            Type::Forall { param, body } => assert_eq!(
+                *body,
+                Type::Closure {
+                    param: Box::new(Type::Var(param.clone())),
+                    body: Box::new(Type::Var(param)),
+                }
+            ),
+            other => panic!("expected a forall type, got {}", other),
+        }
+    }
+
+    #[test]
+    fn it_uses_a_let_bound_identity_function() {
+        // let id = \x. x in id 4
+        let ast = Expr::Let {
+            name: String::from("id"),
+            bound: Box::new(Expr::Abs {
+                param: String::from("x"),
+                param_type: None,
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            body: Box::new(Expr::App {
+                arg: Box::new(Expr::Int {
+                    value: 4,
+                    span: None,
+                }),
+                abs: Box::new(Expr::Var {
+                    name: String::from("id"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
+        assert_eq!(infered_type, Type::Int);
+    }
+
+    #[test]
+    fn it_instantiates_a_let_bound_identity_function_at_different_types() {
+        // let id = \x. x in (id id) 4
+        let ast = Expr::Let {
+            name: String::from("id"),
+            bound: Box::new(Expr::Abs {
+                param: String::from("x"),
+                param_type: None,
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            body: Box::new(Expr::App {
+                arg: Box::new(Expr::Int {
+                    value: 4,
+                    span: None,
+                }),
+                abs: Box::new(Expr::App {
+                    arg: Box::new(Expr::Var {
+                        name: String::from("id"),
+                        span: None,
+                    }),
+                    abs: Box::new(Expr::Var {
+                        name: String::from("id"),
+                        span: None,
+                    }),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let infered_type = infer(ast, TypeContext::new()).unwrap();
+        assert_eq!(infered_type, Type::Int);
+    }
+
+    #[test]
+    fn it_reports_an_unbound_variable_with_its_span() {
+        let ast = Expr::Var {
+            name: String::from("x"),
+            span: Some(Span { start: 0, end: 1 }),
+        };
+
+        let error = infer(ast, TypeContext::new()).unwrap_err();
+
+        assert_eq!(
+            error,
+            Error::UnboundVariable {
+                name: String::from("x"),
+                span: Some(Span { start: 0, end: 1 }),
+            }
+        );
+        assert_eq!(error.render("x"), "unbound variable: x\nx\n^");
+    }
+
+    #[test]
+    fn it_reports_a_type_mismatch_pointing_at_the_argument() {
+        // (\x:Str.x) 1
+        let ast = Expr::App {
+            arg: Box::new(Expr::Int {
+                value: 1,
+                span: Some(Span { start: 11, end: 12 }),
+            }),
+            abs: Box::new(Expr::Abs {
+                param: String::from("x"),
+                param_type: Some(Type::Str),
+                body: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert_eq!(
+            infer(ast, TypeContext::new()),
+            Err(Error::TypeMismatch {
+                expected: Type::Str,
+                actual: Type::Int,
+                span: Some(Span { start: 11, end: 12 }),
+            })
+        );
+    }
+
+    #[test]
+    fn it_reports_not_a_closure_when_applying_a_non_function() {
+        // 4 1
+        let ast = Expr::App {
+            arg: Box::new(Expr::Int {
+                value: 1,
+                span: None,
+            }),
+            abs: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            infer(ast, TypeContext::new()),
+            Err(Error::NotAClosure { .. })
+        ));
+    }
+
+    #[test]
+    fn it_reports_an_invalid_type_application_to_a_non_forall() {
+        // 4 [Int]
+        let ast = Expr::TypeApp {
+            arg: Type::Int,
+            abs: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            infer(ast, TypeContext::new()),
+            Err(Error::InvalidTypeApplication { .. })
+        ));
+    }
+
+    #[test]
+    fn it_reports_not_applicable_when_evaluating_an_application_of_a_non_closure() {
+        // 4 1
+        let ast = Expr::App {
+            arg: Box::new(Expr::Int {
+                value: 1,
+                span: None,
+            }),
+            abs: Box::new(Expr::Int {
+                value: 4,
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            eval(ast, ValueContext::new()),
+            Err(Error::NotApplicable { .. })
+        ));
+    }
+
+    #[test]
+    fn it_rejects_an_abs_annotation_with_an_unbound_type_variable() {
+        // \x:a. x, with no enclosing `Forall a`
+        let ast = Expr::Abs {
+            param: String::from("x"),
+            param_type: Some(Type::Var(String::from("a"))),
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            infer(ast, TypeContext::new()),
+            Err(Error::UnboundTypeVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn it_rejects_a_type_application_with_an_unbound_type_variable() {
+        // (/\a. \x:a. x) [b], with no enclosing `Forall b`
+        let ast = Expr::TypeApp {
+            arg: Type::Var(String::from("b")),
+            abs: Box::new(Expr::TypeAbs {
+                param: String::from("a"),
+                body: Box::new(Expr::Abs {
+                    param: String::from("x"),
+                    param_type: Some(Type::Var(String::from("a"))),
+                    body: Box::new(Expr::Var {
+                        name: String::from("x"),
+                        span: None,
+                    }),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert!(matches!(
+            infer(ast, TypeContext::new()),
+            Err(Error::UnboundTypeVariable { .. })
+        ));
+    }
+
+    #[test]
+    fn it_substitutes_types_without_capturing_a_reused_name() {
+        // forall a. b -> a, replacing `b` with `a` must not let the
+        // substituted `a` be captured by the outer `forall a`.
+        let type_ = Type::Forall {
+            param: String::from("a"),
+            body: Box::new(Type::Closure {
+                param: Box::new(Type::Var(String::from("b"))),
+                body: Box::new(Type::Var(String::from("a"))),
+            }),
+        };
+
+        let replaced = replace_type(&type_, String::from("b"), Type::Var(String::from("a")));
+
+        match replaced {
+            Type::Forall { param, body } => {
+                assert_ne!(param, "a");
+                assert_eq!(
+                    *body,
+                    Type::Closure {
+                        param: Box::new(Type::Var(String::from("a"))),
+                        body: Box::new(Type::Var(param)),
+                    }
+                );
+            }
+            other => panic!("expected a forall type, got {}", other),
+        }
+    }
 }
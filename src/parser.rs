@@ -0,0 +1,578 @@
+use std::fmt::Display;
+
+use crate::{Expr, Span, Type};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    UnexpectedEof,
+    UnexpectedChar { found: char, pos: usize },
+    Expected { expected: String, pos: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            ParseError::UnexpectedEof => String::from("unexpected end of input"),
+            ParseError::UnexpectedChar { found, pos } => {
+                format!("unexpected character '{}' at {}", found, pos)
+            }
+            ParseError::Expected { expected, pos } => {
+                format!("expected {} at {}", expected, pos)
+            }
+        };
+
+        f.write_str(&message)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(input);
+    let expr = parser.parse_expr()?;
+    parser.expect_eof()?;
+
+    Ok(expr)
+}
+
+pub fn parse_type(input: &str) -> Result<Type, ParseError> {
+    let mut parser = Parser::new(input);
+    let type_ = parser.parse_type()?;
+    parser.expect_eof()?;
+
+    Ok(type_)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Parser { input, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.pos += ch.len_utf8();
+        }
+    }
+
+    fn consume(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(ParseError::Expected {
+                expected: token.to_string(),
+                pos: self.pos,
+            })
+        }
+    }
+
+    fn expect_eof(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(()),
+            Some(found) => Err(ParseError::UnexpectedChar {
+                found,
+                pos: self.pos,
+            }),
+        }
+    }
+
+    fn leading_ident(&self) -> Option<&'a str> {
+        let rest = self.rest();
+        let end = rest
+            .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .unwrap_or(rest.len());
+
+        match end {
+            0 => None,
+            _ => Some(&rest[..end]),
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+        match self.leading_ident() {
+            Some(word) if word == keyword => {
+                self.pos += keyword.len();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        match self.leading_ident() {
+            Some(word) => {
+                self.pos += word.len();
+                Ok(word.to_string())
+            }
+            None => Err(match self.peek() {
+                Some(found) => ParseError::UnexpectedChar {
+                    found,
+                    pos: self.pos,
+                },
+                None => ParseError::UnexpectedEof,
+            }),
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<i64, ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.pos += 1;
+        }
+
+        self.input[start..self.pos]
+            .parse()
+            .map_err(|_| ParseError::Expected {
+                expected: String::from("an integer"),
+                pos: start,
+            })
+    }
+
+    fn parse_string_literal(&mut self) -> Result<String, ParseError> {
+        self.expect("\"")?;
+        let start = self.pos;
+
+        while self.peek().is_some_and(|ch| ch != '"') {
+            self.pos += self.peek().unwrap().len_utf8();
+        }
+
+        let value = self.input[start..self.pos].to_string();
+        self.expect("\"")?;
+
+        Ok(value)
+    }
+
+    // type := "forall" ident "." type
+    //       | arrow
+    // arrow := atom ("->" arrow)?
+    // atom := "Int" | "Str" | ident | "(" type ")"
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        if self.consume_keyword("forall") {
+            let param = self.parse_ident()?;
+            self.expect(".")?;
+            let body = self.parse_type()?;
+
+            return Ok(Type::Forall {
+                param,
+                body: Box::new(body),
+            });
+        }
+
+        let atom = self.parse_type_atom()?;
+
+        if self.consume("->") {
+            let body = self.parse_type()?;
+            return Ok(Type::Closure {
+                param: Box::new(atom),
+                body: Box::new(body),
+            });
+        }
+
+        Ok(atom)
+    }
+
+    fn parse_type_atom(&mut self) -> Result<Type, ParseError> {
+        if self.consume("(") {
+            let inner = self.parse_type()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+
+        if self.consume_keyword("Int") {
+            return Ok(Type::Int);
+        }
+
+        if self.consume_keyword("Str") {
+            return Ok(Type::Str);
+        }
+
+        Ok(Type::Var(self.parse_ident()?))
+    }
+
+    // expr := application
+    // application := atom (atom | "[" type "]")*
+    // atom := "(" expr ")"
+    //       | ("\" | "λ") ident (":" type)? "." expr
+    //       | ("/\" | "Λ") ident "." expr
+    //       | "let" ident "=" expr "in" expr
+    //       | int | string | ident
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        self.parse_application()
+    }
+
+    fn parse_application(&mut self) -> Result<Expr, ParseError> {
+        let start = self.pos;
+        let mut expr = self.parse_atom()?;
+
+        loop {
+            if self.consume("[") {
+                let arg = self.parse_type()?;
+                self.expect("]")?;
+                expr = Expr::TypeApp {
+                    arg,
+                    abs: Box::new(expr),
+                    span: Some(Span {
+                        start,
+                        end: self.pos,
+                    }),
+                };
+                continue;
+            }
+
+            if !self.at_atom_start() {
+                break;
+            }
+
+            let arg = self.parse_atom()?;
+            expr = Expr::App {
+                arg: Box::new(arg),
+                abs: Box::new(expr),
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // `in` is the only reserved word that can't also start an atom (`let`
+    // and `forall` are keywords too, but only at positions where an atom or
+    // a type is already expected, so they double as valid atom/var starts
+    // elsewhere).
+    fn at_atom_start(&self) -> bool {
+        let rest = self.rest().trim_start();
+
+        if self.leading_ident_in(rest) == Some("in") {
+            return false;
+        }
+
+        match rest.chars().next() {
+            None => false,
+            Some(ch) => {
+                rest.starts_with("/\\")
+                    || ch == '('
+                    || ch == '\\'
+                    || ch == 'λ'
+                    || ch == 'Λ'
+                    || ch == '"'
+                    || ch.is_ascii_digit()
+                    || ch.is_alphabetic()
+            }
+        }
+    }
+
+    fn leading_ident_in(&self, rest: &'a str) -> Option<&'a str> {
+        let end = rest
+            .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+            .unwrap_or(rest.len());
+
+        match end {
+            0 => None,
+            _ => Some(&rest[..end]),
+        }
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+
+        if self.consume("(") {
+            let inner = self.parse_expr()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+
+        if self.consume("\\") || self.consume("λ") {
+            let param = self.parse_ident()?;
+            let param_type = match self.consume(":") {
+                true => Some(self.parse_type()?),
+                false => None,
+            };
+            self.expect(".")?;
+            let body = self.parse_expr()?;
+
+            return Ok(Expr::Abs {
+                param,
+                param_type,
+                body: Box::new(body),
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            });
+        }
+
+        if self.consume("/\\") || self.consume("Λ") {
+            let param = self.parse_ident()?;
+            self.expect(".")?;
+            let body = self.parse_expr()?;
+
+            return Ok(Expr::TypeAbs {
+                param,
+                body: Box::new(body),
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            });
+        }
+
+        if self.consume_keyword("let") {
+            let name = self.parse_ident()?;
+            self.expect("=")?;
+            let bound = self.parse_expr()?;
+            if !self.consume_keyword("in") {
+                return Err(ParseError::Expected {
+                    expected: String::from("in"),
+                    pos: self.pos,
+                });
+            }
+            let body = self.parse_expr()?;
+
+            return Ok(Expr::Let {
+                name,
+                bound: Box::new(bound),
+                body: Box::new(body),
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            });
+        }
+
+        if self.peek() == Some('"') {
+            let value = self.parse_string_literal()?;
+            return Ok(Expr::Str {
+                value,
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            });
+        }
+
+        if self
+            .peek()
+            .is_some_and(|ch| ch.is_ascii_digit() || ch == '-')
+        {
+            let value = self.parse_int()?;
+            return Ok(Expr::Int {
+                value,
+                span: Some(Span {
+                    start,
+                    end: self.pos,
+                }),
+            });
+        }
+
+        let name = self.parse_ident()?;
+        Ok(Expr::Var {
+            name,
+            span: Some(Span {
+                start,
+                end: self.pos,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip_spans(expr: &Expr) -> Expr {
+        match expr {
+            Expr::Int { value, .. } => Expr::Int {
+                value: *value,
+                span: None,
+            },
+            Expr::Str { value, .. } => Expr::Str {
+                value: value.clone(),
+                span: None,
+            },
+            Expr::Var { name, .. } => Expr::Var {
+                name: name.clone(),
+                span: None,
+            },
+            Expr::Abs {
+                param,
+                param_type,
+                body,
+                ..
+            } => Expr::Abs {
+                param: param.clone(),
+                param_type: param_type.clone(),
+                body: Box::new(strip_spans(body)),
+                span: None,
+            },
+            Expr::TypeAbs { param, body, .. } => Expr::TypeAbs {
+                param: param.clone(),
+                body: Box::new(strip_spans(body)),
+                span: None,
+            },
+            Expr::TypeApp { arg, abs, .. } => Expr::TypeApp {
+                arg: arg.clone(),
+                abs: Box::new(strip_spans(abs)),
+                span: None,
+            },
+            Expr::App { arg, abs, .. } => Expr::App {
+                arg: Box::new(strip_spans(arg)),
+                abs: Box::new(strip_spans(abs)),
+                span: None,
+            },
+            Expr::Let {
+                name, bound, body, ..
+            } => Expr::Let {
+                name: name.clone(),
+                bound: Box::new(strip_spans(bound)),
+                body: Box::new(strip_spans(body)),
+                span: None,
+            },
+        }
+    }
+
+    #[test]
+    fn it_parses_an_annotated_identity_function() {
+        let ast = parse("\\x:Int.x").unwrap();
+        assert_eq!(ast.to_string(), "(λx:Int.x)");
+    }
+
+    #[test]
+    fn it_round_trips_an_unannotated_identity_function() {
+        let ast = Expr::Abs {
+            param: String::from("x"),
+            param_type: None,
+            body: Box::new(Expr::Var {
+                name: String::from("x"),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let reparsed = parse(&ast.to_string()).unwrap();
+        assert_eq!(strip_spans(&reparsed), ast);
+    }
+
+    #[test]
+    fn it_round_trips_a_polymorphic_identity_function_applied_to_a_type() {
+        let ast = Expr::TypeApp {
+            arg: Type::Int,
+            abs: Box::new(Expr::TypeAbs {
+                param: String::from("a"),
+                body: Box::new(Expr::Abs {
+                    param: String::from("x"),
+                    param_type: Some(Type::Var(String::from("a"))),
+                    body: Box::new(Expr::Var {
+                        name: String::from("x"),
+                        span: None,
+                    }),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        let reparsed = parse(&ast.to_string()).unwrap();
+        assert_eq!(strip_spans(&reparsed), ast);
+    }
+
+    #[test]
+    fn it_parses_application_left_associatively() {
+        let ast = strip_spans(&parse("f x y").unwrap());
+
+        let expected = Expr::App {
+            arg: Box::new(Expr::Var {
+                name: String::from("y"),
+                span: None,
+            }),
+            abs: Box::new(Expr::App {
+                arg: Box::new(Expr::Var {
+                    name: String::from("x"),
+                    span: None,
+                }),
+                abs: Box::new(Expr::Var {
+                    name: String::from("f"),
+                    span: None,
+                }),
+                span: None,
+            }),
+            span: None,
+        };
+
+        assert_eq!(ast, expected);
+    }
+
+    #[test]
+    fn it_parses_let_expressions() {
+        let ast = parse("let id = \\x.x in id 4").unwrap();
+        assert_eq!(ast.to_string(), "(let id = (λx.x) in (id 4))");
+    }
+
+    #[test]
+    fn it_parses_string_literals() {
+        let ast = parse("\"hello\"").unwrap();
+        assert_eq!(ast.to_string(), "\"hello\"");
+    }
+
+    #[test]
+    fn it_parses_types() {
+        assert_eq!(parse_type("Int").unwrap(), Type::Int);
+        assert_eq!(parse_type("Str").unwrap(), Type::Str);
+        assert_eq!(
+            parse_type("a -> b").unwrap(),
+            Type::Closure {
+                param: Box::new(Type::Var(String::from("a"))),
+                body: Box::new(Type::Var(String::from("b"))),
+            }
+        );
+        assert_eq!(
+            parse_type("forall a. a -> a").unwrap(),
+            Type::Forall {
+                param: String::from("a"),
+                body: Box::new(Type::Closure {
+                    param: Box::new(Type::Var(String::from("a"))),
+                    body: Box::new(Type::Var(String::from("a"))),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn it_reports_an_error_for_unbalanced_parens() {
+        assert!(parse("(\\x.x").is_err());
+    }
+}